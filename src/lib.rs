@@ -1,9 +1,11 @@
 #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
 
 extern crate wasm_bindgen;
+extern crate web_sys;
 
 use std::fmt;
 use wasm_bindgen::prelude::*;
+use web_sys::console;
 
 #[wasm_bindgen]
 extern "C" {
@@ -22,19 +24,175 @@ macro_rules! log {
     ($($t:tt)*) => {}; // ($($t: tt)*) => (log(&format!($($t)*)))
 }
 
-#[repr(u8)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum Cell {
-    Dead = 0,
-    Alive = 1,
+// Wraps a `console.time`/`console.timeEnd` pair for the devtools timeline;
+// ends automatically when dropped at the end of the scope it times.
+pub struct Timer<'a> {
+    name: &'a str,
 }
 
-impl Cell {
-    fn toggle(&mut self) {
-        *self = match *self {
-            Cell::Dead => Cell::Alive,
-            Cell::Alive => Cell::Dead,
-        };
+impl<'a> Timer<'a> {
+    fn new(name: &'a str) -> Timer<'a> {
+        console::time_with_label(name);
+        Timer { name }
+    }
+}
+
+impl<'a> Drop for Timer<'a> {
+    fn drop(&mut self) {
+        console::time_end_with_label(self.name);
+    }
+}
+
+fn now_ms() -> f64 {
+    web_sys::window()
+        .and_then(|window| window.performance())
+        .map(|performance| performance.now())
+        .unwrap_or(0.0)
+}
+
+// Species 0 is always "empty"; 1..N_SPECIES are populated states.
+const N_SPECIES: usize = 4;
+
+type Transition = fn(u8, &[u8; N_SPECIES], &Rule) -> u8;
+
+// Classic Life, played out on species 1 alone; every other species stays put.
+fn life_transition(current: u8, counts: &[u8; N_SPECIES], rule: &Rule) -> u8 {
+    if current != 0 && current != 1 {
+        return current;
+    }
+
+    let n = counts[1] as usize;
+    let alive = current == 1;
+
+    if (alive && rule.survive[n]) || (!alive && rule.birth[n]) {
+        1
+    } else {
+        0
+    }
+}
+
+// Two competing species: an empty cell whose populated-neighbor count is in
+// the rule's birth set (exactly 3 under B3/S23) is born into whichever of
+// species 1/2 holds the majority of those neighbors; a tie goes to species 2.
+fn two_species_majority_transition(current: u8, counts: &[u8; N_SPECIES], rule: &Rule) -> u8 {
+    let n = (counts[1] + counts[2]) as usize;
+
+    if current != 0 {
+        return if rule.survive[n] { current } else { 0 };
+    }
+
+    if rule.birth[n] {
+        if counts[1] > counts[2] {
+            1
+        } else {
+            2
+        }
+    } else {
+        0
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Rule {
+    birth: [bool; 9],
+    survive: [bool; 9],
+}
+
+impl Rule {
+    fn life() -> Rule {
+        Rule::parse("B3/S23").unwrap()
+    }
+
+    fn parse(rulestring: &str) -> Option<Rule> {
+        let (b_part, s_part) = rulestring.split_once('/')?;
+
+        if !(b_part.starts_with('B') || b_part.starts_with('b')) {
+            return None;
+        }
+        if !(s_part.starts_with('S') || s_part.starts_with('s')) {
+            return None;
+        }
+
+        let mut birth = [false; 9];
+        let mut survive = [false; 9];
+
+        for ch in b_part[1..].chars() {
+            let n = ch.to_digit(10)? as usize;
+            if n < 9 {
+                birth[n] = true;
+            }
+        }
+
+        for ch in s_part[1..].chars() {
+            let n = ch.to_digit(10)? as usize;
+            if n < 9 {
+                survive[n] = true;
+            }
+        }
+
+        Some(Rule { birth, survive })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "B")?;
+        for n in 0..9 {
+            if self.birth[n] {
+                write!(f, "{}", n)?;
+            }
+        }
+
+        write!(f, "/S")?;
+        for n in 0..9 {
+            if self.survive[n] {
+                write!(f, "{}", n)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod rule_tests {
+    use super::{Rule, Universe};
+
+    #[test]
+    fn parse_is_case_insensitive() {
+        let upper = Rule::parse("B3/S23").unwrap();
+        let lower = Rule::parse("b3/s23").unwrap();
+
+        assert_eq!(upper.birth, lower.birth);
+        assert_eq!(upper.survive, lower.survive);
+    }
+
+    #[test]
+    fn parse_rejects_a_string_with_no_slash() {
+        assert!(Rule::parse("B3S23").is_none());
+    }
+
+    #[test]
+    fn parse_drops_digits_outside_the_birth_survive_array() {
+        // The array only has room for neighbor counts 0..=8; a rulestring
+        // naming 9 (not a valid neighbor count on a Moore neighborhood of 8)
+        // must be silently dropped rather than panicking on an out-of-bounds
+        // index.
+        let rule = Rule::parse("B39/S23").unwrap();
+
+        assert!(rule.birth[3]);
+        assert!(!rule.birth.iter().skip(4).any(|&b| b));
+    }
+
+    #[test]
+    fn default_life_rule_matches_its_rulestring() {
+        assert_eq!(Rule::life().to_string(), "B3/S23");
+    }
+
+    #[test]
+    fn header_without_a_rule_clause_has_no_rule() {
+        let (_, _, rule) = Universe::parse_rle_header("x = 3, y = 3\nbob$2bo$3o!");
+        assert!(rule.is_none());
     }
 }
 
@@ -42,8 +200,26 @@ impl Cell {
 pub struct Universe {
     width: u32,
     height: u32,
-    cells: [Vec<Cell>; 2],
+    // Species id (0..N_SPECIES) per cell, packed 4 cells to a byte: 2 bits
+    // per cell, bits `(idx & 3) * 2` of byte `idx >> 2`.
+    cells: [Vec<u8>; 2],
     cells_idx: usize,
+    rule: Rule,
+    transition: Transition,
+    // How many species `toggle_cell` cycles through for the active ruleset
+    // (life: empty/alive; majority: empty plus its two competing species).
+    // Anything `set_ruleset` doesn't recognize falls back to `life_transition`,
+    // which ignores species other than 0/1 forever, so toggling must never
+    // cycle a cell past the species the active ruleset actually understands.
+    active_species: u8,
+    // How many consecutive generations each cell has been populated for.
+    age: [Vec<u16>; 2],
+    generation: u64,
+    last_tick_ms: f64,
+    avg_tick_ms: f64,
+    // Kept in sync by `tick`/`toggle_cell` rather than recomputed on demand,
+    // so that polling it once per frame doesn't double the cost of a tick.
+    live_cells: u32,
 }
 
 #[wasm_bindgen]
@@ -55,26 +231,181 @@ impl Universe {
             height = height / 8 * 8;
         }
 
-        let cells = (0..width * height)
-            .map(|_| {
-                if random() < 0.5 {
-                    Cell::Alive
-                } else {
-                    Cell::Dead
-                }
-            })
-            .collect();
+        Universe::with_dimensions(width, height)
+    }
+
+    // Builds a universe of exactly `width` x `height`, randomly seeded. The
+    // caller is responsible for `width`/`height` already being whatever
+    // multiple of 8 it wants enforced (`new` rounds down with a user-facing
+    // alert; `from_rle` rounds up silently to avoid truncating a pattern).
+    fn with_dimensions(width: u32, height: u32) -> Universe {
+        let len_bytes = Universe::bytes_len(width, height);
+        let mut cells = vec![0u8; len_bytes];
+        let mut live_cells = 0;
+
+        for idx in 0..(width * height) as usize {
+            let species = if random() < 0.5 { 1 } else { 0 };
+            Universe::set_cell_at(&mut cells, idx, species);
+            if species != 0 {
+                live_cells += 1;
+            }
+        }
+
+        let cells_back = vec![0u8; len_bytes];
 
-        let cells_back = vec![Cell::Alive; (width * height) as usize];
+        let age = vec![0u16; (width * height) as usize];
+        let age_back = age.clone();
 
         Universe {
             width,
             height,
             cells: [cells, cells_back],
             cells_idx: 0,
+            rule: Rule::life(),
+            transition: life_transition,
+            active_species: 2,
+            age: [age, age_back],
+            generation: 0,
+            last_tick_ms: 0.0,
+            avg_tick_ms: 0.0,
+            live_cells,
         }
     }
 
+    pub fn set_rule(&mut self, rulestring: &str) {
+        if let Some(rule) = Rule::parse(rulestring) {
+            self.rule = rule;
+        }
+    }
+
+    pub fn rule(&self) -> String {
+        self.rule.to_string()
+    }
+
+    pub fn set_ruleset(&mut self, name: &str) {
+        let (transition, active_species): (Transition, u8) = match name {
+            "majority" | "two-species" => (two_species_majority_transition, 3),
+            _ => (life_transition, 2),
+        };
+        self.transition = transition;
+        self.active_species = active_species;
+    }
+
+    pub fn from_rle(rle: &str) -> Universe {
+        let (width, height, rule) = Universe::parse_rle_header(rle);
+
+        // Round up (never down) so the declared pattern always fits; `new`'s
+        // down-rounding would silently truncate or even zero out most
+        // real-world RLE dimensions, which aren't multiples of 8.
+        let width = Universe::round_up_to_8(width.max(1));
+        let height = Universe::round_up_to_8(height.max(1));
+
+        let mut universe = Universe::with_dimensions(width, height);
+
+        for idx in 0..(universe.width * universe.height) as usize {
+            universe.set_cell(idx, 0);
+        }
+
+        if let Some(rulestring) = rule {
+            universe.set_rule(&rulestring);
+        }
+
+        universe.load_rle(rle, 0, 0);
+        universe
+    }
+
+    fn round_up_to_8(n: u32) -> u32 {
+        n.div_ceil(8) * 8
+    }
+
+    pub fn load_rle(&mut self, rle: &str, origin_row: u32, origin_col: u32) {
+        let body = Universe::rle_body(rle);
+
+        let mut row = origin_row;
+        let mut col = origin_col;
+        let mut count: u32 = 0;
+
+        for ch in body.chars() {
+            match ch {
+                '0'..='9' => count = count * 10 + ch.to_digit(10).unwrap(),
+                'b' | 'o' => {
+                    let run = if count == 0 { 1 } else { count };
+
+                    if ch == 'o' {
+                        for _ in 0..run {
+                            if row < self.height && col < self.width {
+                                let idx = self.get_index(row, col);
+                                self.set_cell(idx, 1);
+                            }
+                            col += 1;
+                        }
+                    } else {
+                        col += run;
+                    }
+
+                    count = 0;
+                }
+                '$' => {
+                    let run = if count == 0 { 1 } else { count };
+                    row += run;
+                    col = origin_col;
+                    count = 0;
+                }
+                '!' => break,
+                _ => {} // whitespace and anything else between tokens is ignored
+            }
+        }
+    }
+
+    fn parse_rle_header(rle: &str) -> (u32, u32, Option<String>) {
+        for line in rle.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut width = 0;
+            let mut height = 0;
+            let mut rule = None;
+
+            for field in line.split(',') {
+                let mut parts = field.splitn(2, '=');
+                let key = parts.next().unwrap_or("").trim();
+                let value = parts.next().unwrap_or("").trim();
+
+                match key {
+                    "x" => width = value.parse().unwrap_or(0),
+                    "y" => height = value.parse().unwrap_or(0),
+                    "rule" => rule = Some(value.to_string()),
+                    _ => {}
+                }
+            }
+
+            return (width, height, rule);
+        }
+
+        (0, 0, None)
+    }
+
+    fn rle_body(rle: &str) -> String {
+        let mut found_header = false;
+        let mut body = String::new();
+
+        for line in rle.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            if !found_header {
+                found_header = true;
+                continue;
+            }
+            body.push_str(trimmed);
+        }
+
+        body
+    }
+
     pub fn width(&self) -> u32 {
         self.width
     }
@@ -83,47 +414,102 @@ impl Universe {
         self.height
     }
 
-    pub fn cells_ptr(&self) -> *const Cell {
+    pub fn cells_ptr(&self) -> *const u8 {
         self.cells().as_ptr()
     }
 
+    pub fn cells_len_bytes(&self) -> usize {
+        self.cells().len()
+    }
+
+    // Same packed buffer as `cells_ptr`, named for callers that render each
+    // cell's species rather than a plain alive/dead bit.
+    pub fn species_ptr(&self) -> *const u8 {
+        self.cells_ptr()
+    }
+
+    pub fn age_ptr(&self) -> *const u16 {
+        self.age[self.cells_idx].as_ptr()
+    }
+
+    pub fn max_age(&self) -> u16 {
+        self.age[self.cells_idx].iter().cloned().max().unwrap_or(0)
+    }
+
     pub fn tick(&mut self) {
+        let _timer = Timer::new("Universe::tick");
+        let start = now_ms();
+
         let new_cells_idx = self.cells_idx ^ 1;
+        let mut live_cells = 0;
 
         for row in 0..self.height {
             for col in 0..self.width {
                 let idx = self.get_index(row, col);
-                let cell = self.cells()[idx];
-                let live_neighbors = self.live_neighbor_count(row, col);
+                let species = self.get_cell(idx);
+                let counts = self.neighbor_counts(row, col);
 
                 log!(
-                    "cell[{}, {}] is initially {:?} and has {} live neighbors",
+                    "cell[{}, {}] is initially species {} with neighbor counts {:?}",
                     row,
                     col,
-                    cell,
-                    live_neighbors
+                    species,
+                    counts
                 );
 
-                let next_cell = match (cell, live_neighbors) {
-                    (Cell::Alive, x) if x < 2 => Cell::Dead,
-                    (Cell::Alive, x) if x < 4 => Cell::Alive,
-                    (Cell::Alive, _) => Cell::Dead,
-                    (Cell::Dead, 3) => Cell::Alive,
-                    (otherwise, _) => otherwise,
-                };
+                let next_species = (self.transition)(species, &counts, &self.rule);
+
+                log!("   it becomes species {}", next_species);
 
-                log!("   it becomes {:?}", next_cell);
+                Universe::set_cell_at(&mut self.cells[new_cells_idx], idx, next_species);
+
+                self.age[new_cells_idx][idx] = if next_species != 0 {
+                    self.age[self.cells_idx][idx].saturating_add(1)
+                } else {
+                    0
+                };
 
-                self.cells[new_cells_idx][idx] = next_cell;
+                if next_species != 0 {
+                    live_cells += 1;
+                }
             }
         }
 
         self.cells_idx = new_cells_idx;
+        self.live_cells = live_cells;
+
+        self.last_tick_ms = now_ms() - start;
+        self.generation += 1;
+        self.avg_tick_ms += (self.last_tick_ms - self.avg_tick_ms) / self.generation as f64;
+    }
+
+    pub fn last_tick_ms(&self) -> f64 {
+        self.last_tick_ms
+    }
+
+    pub fn avg_tick_ms(&self) -> f64 {
+        self.avg_tick_ms
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn live_cell_count(&self) -> u32 {
+        self.live_cells
     }
 
     pub fn toggle_cell(&mut self, row: u32, column: u32) {
         let idx = self.get_index(row, column);
-        self.cells_mut()[idx].toggle();
+        let species = self.get_cell(idx);
+        let next_species = (species + 1) % self.active_species;
+        self.set_cell(idx, next_species);
+
+        // A manual toggle starts a fresh lifetime for the cell; otherwise
+        // age_ptr()/max_age() would report whatever age this slot happened
+        // to have left over from before it last died.
+        let cells_idx = self.cells_idx;
+        self.age[cells_idx][idx] = if next_species == 0 { 0 } else { 1 };
     }
 
     pub fn render(&self) -> String {
@@ -134,15 +520,44 @@ impl Universe {
         (row * self.width + column) as usize
     }
 
-    fn cells(&self) -> &[Cell] {
+    fn bytes_len(width: u32, height: u32) -> usize {
+        (width * height).div_ceil(4) as usize
+    }
+
+    fn cell_at(cells: &[u8], idx: usize) -> u8 {
+        let shift = (idx & 3) * 2;
+        (cells[idx >> 2] >> shift) & 0b11
+    }
+
+    fn set_cell_at(cells: &mut [u8], idx: usize, species: u8) {
+        let shift = (idx & 3) * 2;
+        let mask = 0b11u8 << shift;
+        cells[idx >> 2] = (cells[idx >> 2] & !mask) | ((species & 0b11) << shift);
+    }
+
+    fn cells(&self) -> &[u8] {
         self.cells[self.cells_idx].as_slice()
     }
 
-    fn cells_mut(&mut self) -> &mut [Cell] {
-        self.cells[self.cells_idx].as_mut_slice()
+    fn get_cell(&self, idx: usize) -> u8 {
+        Universe::cell_at(self.cells(), idx)
+    }
+
+    fn set_cell(&mut self, idx: usize, species: u8) {
+        let was_live = self.get_cell(idx) != 0;
+        let is_live = species != 0;
+
+        let cells_idx = self.cells_idx;
+        Universe::set_cell_at(&mut self.cells[cells_idx], idx, species);
+
+        match (was_live, is_live) {
+            (false, true) => self.live_cells += 1,
+            (true, false) => self.live_cells -= 1,
+            _ => {}
+        }
     }
 
-    fn live_neighbor_count(&self, row: u32, column: u32) -> u8 {
+    fn neighbor_counts(&self, row: u32, column: u32) -> [u8; N_SPECIES] {
         let north = if row == 0 { self.height - 1 } else { row - 1 };
 
         let south = if row == self.height - 1 { 0 } else { row + 1 };
@@ -159,44 +574,37 @@ impl Universe {
             column + 1
         };
 
-        let mut count = 0;
-
-        let nw = self.get_index(north, west);
-        count += self.cells()[nw] as u8;
-
-        let n = self.get_index(north, column);
-        count += self.cells()[n] as u8;
-
-        let ne = self.get_index(north, east);
-        count += self.cells()[ne] as u8;
-
-        let w = self.get_index(row, west);
-        count += self.cells()[w] as u8;
-
-        let e = self.get_index(row, east);
-        count += self.cells()[e] as u8;
-
-        let sw = self.get_index(south, west);
-        count += self.cells()[sw] as u8;
-
-        let s = self.get_index(south, column);
-        count += self.cells()[s] as u8;
-
-        let se = self.get_index(south, east);
-        count += self.cells()[se] as u8;
+        let mut counts = [0u8; N_SPECIES];
+
+        let neighbors = [
+            self.get_index(north, west),
+            self.get_index(north, column),
+            self.get_index(north, east),
+            self.get_index(row, west),
+            self.get_index(row, east),
+            self.get_index(south, west),
+            self.get_index(south, column),
+            self.get_index(south, east),
+        ];
+
+        for idx in neighbors.iter() {
+            counts[self.get_cell(*idx) as usize] += 1;
+        }
 
-        count
+        counts
     }
 }
 
 impl fmt::Display for Universe {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for line in self.cells().chunks(self.width as usize) {
-            for &cell in line {
-                let symbol = if cell == Cell::Dead {
-                    "◼️"
-                } else {
-                    "◻️"
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let idx = self.get_index(row, col);
+                let symbol = match self.get_cell(idx) {
+                    0 => "◼️",
+                    1 => "◻️",
+                    2 => "🟥",
+                    _ => "🟦",
                 };
                 write!(f, "{}", symbol).unwrap();
             }
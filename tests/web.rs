@@ -0,0 +1,132 @@
+extern crate wasm_bindgen_test;
+extern crate wasm_game_of_life;
+
+use wasm_bindgen_test::*;
+use wasm_game_of_life::Universe;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn species_at(universe: &Universe, idx: usize) -> u8 {
+    let bytes =
+        unsafe { std::slice::from_raw_parts(universe.species_ptr(), universe.cells_len_bytes()) };
+    let shift = (idx & 3) * 2;
+    (bytes[idx >> 2] >> shift) & 0b11
+}
+
+#[wasm_bindgen_test]
+fn from_rle_sizes_universe_to_fit_the_pattern() {
+    // A standard glider: 3x3, five live cells.
+    let glider = "x = 3, y = 3, rule = B3/S23\nbob$2bo$3o!";
+    let universe = Universe::from_rle(glider);
+
+    assert!(universe.width() >= 3 && universe.width() % 8 == 0);
+    assert!(universe.height() >= 3 && universe.height() % 8 == 0);
+    assert_eq!(universe.live_cell_count(), 5);
+}
+
+#[wasm_bindgen_test]
+fn from_rle_does_not_clip_dimensions_that_are_not_multiples_of_8() {
+    // Gosper glider gun: 36x9, would become 32x8 (clipped) if rounded down.
+    let gun_header = "x = 36, y = 9, rule = B3/S23\n";
+    let universe = Universe::from_rle(gun_header);
+
+    assert!(universe.width() >= 36);
+    assert!(universe.height() >= 9);
+}
+
+#[wasm_bindgen_test]
+fn toggled_cells_pack_correctly_across_a_byte_boundary() {
+    // Blank 8x8 grid: cells 0..4 share byte 0, cells 4..8 share byte 1.
+    let mut universe = Universe::from_rle("x = 8, y = 8\n!");
+    // The life ruleset only cycles 0/1; majority lets a toggle reach species 2.
+    universe.set_ruleset("majority");
+
+    universe.toggle_cell(0, 3); // idx 3: last cell of byte 0
+    universe.toggle_cell(0, 4); // idx 4: first cell of byte 1
+    universe.toggle_cell(0, 4); // toggle again so idx 4 lands on species 2
+
+    let bytes =
+        unsafe { std::slice::from_raw_parts(universe.species_ptr(), universe.cells_len_bytes()) };
+
+    assert_eq!((bytes[0] >> 6) & 0b11, 1); // idx 3
+    assert_eq!((bytes[0] >> 4) & 0b11, 0); // idx 2, untouched neighbor
+    assert_eq!(bytes[1] & 0b11, 2); // idx 4
+    assert_eq!(universe.live_cell_count(), 2);
+}
+
+#[wasm_bindgen_test]
+fn age_increases_each_tick_for_a_surviving_cell() {
+    let mut universe = Universe::from_rle("x = 8, y = 8\n!");
+    // Never born, always survives once alive: an isolated cell neither dies
+    // nor spawns neighbors, so its age climbs by exactly one per tick.
+    universe.set_rule("B/S012345678");
+    universe.toggle_cell(0, 0);
+
+    assert_eq!(universe.max_age(), 1);
+
+    for expected_age in 2..=4 {
+        universe.tick();
+        assert_eq!(universe.max_age(), expected_age);
+    }
+
+    assert_eq!(universe.generation(), 3);
+
+    let ages = unsafe {
+        std::slice::from_raw_parts(universe.age_ptr(), (universe.width() * universe.height()) as usize)
+    };
+    assert_eq!(ages[0], 4);
+}
+
+#[wasm_bindgen_test]
+fn toggle_cell_cycles_within_the_life_rulesets_species_count() {
+    let mut universe = Universe::from_rle("x = 8, y = 8\n!");
+
+    universe.toggle_cell(0, 0);
+    assert_eq!(species_at(&universe, 0), 1);
+
+    // A second click must cycle back to empty, not advance to species 2:
+    // `life_transition` only understands species 0/1, so anything else
+    // would freeze on the cell forever.
+    universe.toggle_cell(0, 0);
+    assert_eq!(species_at(&universe, 0), 0);
+}
+
+#[wasm_bindgen_test]
+fn majority_ruleset_births_the_majority_species() {
+    let mut universe = Universe::from_rle("x = 8, y = 8\n!");
+    universe.set_ruleset("majority");
+
+    universe.toggle_cell(0, 0); // species 1
+    universe.toggle_cell(0, 1); // species 1
+    universe.toggle_cell(1, 0); // species 1
+    universe.toggle_cell(1, 0); // toggle again -> species 2
+
+    universe.tick();
+
+    // (1, 1) is empty with neighbors (0,0)=1, (0,1)=1, (1,0)=2: species 1 is
+    // the majority of its 3 populated neighbors, so it's born as species 1.
+    // idx = row * width + column = 1 * 8 + 1, with width == 8.
+    assert_eq!(species_at(&universe, 9), 1);
+}
+
+#[wasm_bindgen_test]
+fn tick_updates_timing_and_generation_stats() {
+    let mut universe = Universe::from_rle("x = 8, y = 8\n!");
+    assert_eq!(universe.generation(), 0);
+    assert_eq!(universe.live_cell_count(), 0);
+
+    universe.toggle_cell(0, 0);
+    assert_eq!(universe.live_cell_count(), 1);
+
+    // Never born, always survives once alive, so live_cell_count stays
+    // exactly 1 across ticks while generation keeps climbing.
+    universe.set_rule("B/S012345678");
+    for expected_generation in 1..=3 {
+        universe.tick();
+        assert_eq!(universe.generation(), expected_generation);
+        assert_eq!(universe.live_cell_count(), 1);
+    }
+
+    assert!(universe.last_tick_ms() >= 0.0);
+    assert!(universe.avg_tick_ms() >= 0.0);
+}